@@ -0,0 +1,221 @@
+use chain_model::block::{approval_message, Approval, ApprovalInner, VerifiedVote};
+use chain_model::crypto::CryptoHash;
+use chain_model::types::{AccountId, Balance, BlockHeight};
+use chain_model::validator_set::ValidatorSet;
+use ed25519_dalek::Verifier;
+use std::collections::{HashMap, HashSet};
+
+/// Tracks stake-weighted endorsements/skips received from other validators and decides when a
+/// block has crossed the Doomslug (>=1/2 stake) or full (>=2/3 stake) finality thresholds.
+///
+/// This is the consumer counterpart to `Doomslug::process_timer`: that produces approvals, this
+/// ingests approvals received from the network and turns accumulated stake into finality
+/// decisions.
+pub struct DoomslugApprovalTracker {
+    validators: ValidatorSet,
+    /// Stake accumulated so far for each `(inner, target_height)` key.
+    approved_stake: HashMap<(ApprovalInner, BlockHeight), Balance>,
+    /// Validators already counted towards a given key, to guard against double counting.
+    voted: HashMap<(ApprovalInner, BlockHeight), HashSet<AccountId>>,
+}
+
+impl DoomslugApprovalTracker {
+    pub fn new(validators: ValidatorSet) -> Self {
+        Self {
+            validators,
+            approved_stake: HashMap::new(),
+            voted: HashMap::new(),
+        }
+    }
+
+    /// Registers an incoming approval. Ignored if the signer isn't a known validator, if its
+    /// signature doesn't check out against the validator's public key, or if it has already been
+    /// counted for this exact `(inner, target_height)` key -- note that an
+    /// `Endorsement(parent_hash)` vote is keyed by its specific parent, so it can never be
+    /// counted towards a different parent's finality.
+    pub fn register_approval(&mut self, approval: &Approval) {
+        let Some(validator) = self.validators.get(&approval.account_id) else {
+            return;
+        };
+        let message = approval_message(&approval.inner, approval.target_height);
+        if validator
+            .public_key
+            .verify(message.as_ref(), &approval.signature)
+            .is_err()
+        {
+            return;
+        }
+        self.credit(&approval.account_id, validator.stake, approval.inner.clone(), approval.target_height);
+    }
+
+    /// Registers a vote that was already authenticated elsewhere, e.g. one expanded out of a
+    /// `CoalescedApproval` by `CoalescedApproval::verify_and_expand` -- its batch signature isn't
+    /// a valid signature over this vote alone, so there's nothing left to re-verify here.
+    pub fn register_verified_vote(&mut self, vote: &VerifiedVote) {
+        let Some(stake) = self.validators.stake_of(&vote.account_id) else {
+            return;
+        };
+        self.credit(&vote.account_id, stake, vote.inner.clone(), vote.target_height);
+    }
+
+    fn credit(
+        &mut self,
+        account_id: &AccountId,
+        stake: Balance,
+        inner: ApprovalInner,
+        target_height: BlockHeight,
+    ) {
+        let key = (inner, target_height);
+        if !self.voted.entry(key.clone()).or_default().insert(account_id.clone()) {
+            return;
+        }
+        *self.approved_stake.entry(key).or_insert(0) += stake;
+    }
+
+    fn endorsing_stake(&self, parent_hash: CryptoHash, target_height: BlockHeight) -> Balance {
+        let key = (ApprovalInner::Endorsement(parent_hash), target_height);
+        *self.approved_stake.get(&key).unwrap_or(&0)
+    }
+
+    /// True once endorsements of `parent_hash` at `target_height` cross the >=1/2 stake
+    /// threshold: Doomslug finality. The caller should feed a height that satisfies this back
+    /// into `Doomslug::update_largest_final_height` and use it as `last_final_height` on the
+    /// next `Doomslug::set_tip`.
+    pub fn can_approved_block_be_produced(
+        &self,
+        parent_hash: CryptoHash,
+        target_height: BlockHeight,
+    ) -> bool {
+        let total = self.validators.total_stake();
+        total > 0 && self.endorsing_stake(parent_hash, target_height) * 2 >= total
+    }
+
+    /// True once endorsements of `parent_hash` at `target_height` cross the >=2/3 stake
+    /// threshold: full finality.
+    pub fn is_fully_final(&self, parent_hash: CryptoHash, target_height: BlockHeight) -> bool {
+        let total = self.validators.total_stake();
+        total > 0 && self.endorsing_stake(parent_hash, target_height) * 3 >= total * 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_model::crypto::hash;
+    use chain_model::validator_signer::ValidatorSigner;
+    use chain_model::validator_set::ValidatorStake;
+    use ed25519_dalek::SigningKey;
+
+    fn validator_set() -> ValidatorSet {
+        ValidatorSet::new(vec![
+            ValidatorStake {
+                account_id: "alice.near".to_string(),
+                stake: 50,
+                public_key: signer("alice.near", 1).public_key(),
+            },
+            ValidatorStake {
+                account_id: "bob.near".to_string(),
+                stake: 30,
+                public_key: signer("bob.near", 2).public_key(),
+            },
+            ValidatorStake {
+                account_id: "carol.near".to_string(),
+                stake: 20,
+                public_key: signer("carol.near", 3).public_key(),
+            },
+        ])
+    }
+
+    fn signer(account_id: &str, seed: u8) -> ValidatorSigner {
+        ValidatorSigner::new(account_id.to_string(), SigningKey::from_bytes(&[seed; 32]))
+    }
+
+    #[test]
+    fn crosses_half_stake_threshold_but_not_two_thirds() {
+        let mut tracker = DoomslugApprovalTracker::new(validator_set());
+        let parent = hash(&[1]);
+
+        let alice = signer("alice.near", 1);
+        let approval = Approval::new(parent, 10, 11, &alice);
+        tracker.register_approval(&approval);
+
+        // Alice alone holds exactly half the stake (50/100).
+        assert!(tracker.can_approved_block_be_produced(parent, 11));
+        assert!(!tracker.is_fully_final(parent, 11));
+
+        let bob = signer("bob.near", 2);
+        let approval = Approval::new(parent, 10, 11, &bob);
+        tracker.register_approval(&approval);
+
+        // Alice + Bob hold 80/100 stake, above the 2/3 threshold.
+        assert!(tracker.is_fully_final(parent, 11));
+    }
+
+    #[test]
+    fn ignores_double_votes_from_the_same_validator() {
+        let mut tracker = DoomslugApprovalTracker::new(validator_set());
+        let parent = hash(&[1]);
+        let alice = signer("alice.near", 1);
+
+        tracker.register_approval(&Approval::new(parent, 10, 11, &alice));
+        tracker.register_approval(&Approval::new(parent, 10, 11, &alice));
+
+        assert!(tracker.can_approved_block_be_produced(parent, 11));
+        assert!(!tracker.is_fully_final(parent, 11));
+    }
+
+    #[test]
+    fn endorsement_does_not_count_towards_a_different_parent() {
+        let mut tracker = DoomslugApprovalTracker::new(validator_set());
+        let alice = signer("alice.near", 1);
+        let bob = signer("bob.near", 2);
+
+        tracker.register_approval(&Approval::new(hash(&[1]), 10, 11, &alice));
+        tracker.register_approval(&Approval::new(hash(&[1]), 10, 11, &bob));
+
+        // Both endorsed `hash(&[1])`; a different parent at the same target height has no stake.
+        assert!(!tracker.can_approved_block_be_produced(hash(&[2]), 11));
+    }
+
+    #[test]
+    fn unknown_validator_is_ignored() {
+        let mut tracker = DoomslugApprovalTracker::new(validator_set());
+        let parent = hash(&[1]);
+        let stranger = signer("stranger.near", 9);
+
+        tracker.register_approval(&Approval::new(parent, 10, 11, &stranger));
+
+        assert!(!tracker.can_approved_block_be_produced(parent, 11));
+    }
+
+    #[test]
+    fn forged_account_id_with_mismatched_signature_is_ignored() {
+        let mut tracker = DoomslugApprovalTracker::new(validator_set());
+        let parent = hash(&[1]);
+
+        // Signed by a validator not in the set, but claiming to be alice: the signature won't
+        // verify against alice's public key, so it must not count towards her stake.
+        let forger = signer("mallory.near", 9);
+        let mut forged = Approval::new(parent, 10, 11, &forger);
+        forged.account_id = "alice.near".to_string();
+        tracker.register_approval(&forged);
+
+        assert!(!tracker.can_approved_block_be_produced(parent, 11));
+    }
+
+    #[test]
+    fn register_verified_vote_credits_stake_without_an_individual_signature() {
+        use chain_model::block::VerifiedVote;
+
+        let mut tracker = DoomslugApprovalTracker::new(validator_set());
+        let parent = hash(&[1]);
+
+        tracker.register_verified_vote(&VerifiedVote {
+            inner: ApprovalInner::Endorsement(parent),
+            target_height: 11,
+            account_id: "alice.near".to_string(),
+        });
+
+        assert!(tracker.can_approved_block_be_produced(parent, 11));
+    }
+}
@@ -1,21 +1,34 @@
-use chain_model::block::Approval;
-use chain_model::clock::Clock;
+use arc_swap::ArcSwap;
+use chain_model::block::{Approval, ApprovalInner, CoalescedApproval};
+use chain_model::clock::{Clock, Tick};
 use chain_model::crypto::CryptoHash;
 use chain_model::types::{BlockHeight, BlockHeightDelta};
+use chain_model::validator_signer::ValidatorSigner;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// A hot-swappable handle to the validator signer. Wrapped in an `ArcSwap` (rather than being
+/// a plain field on `Doomslug`) so that the validator key can be rotated at runtime without
+/// rebuilding the `Doomslug` instance.
+pub type ValidatorSignerHandle = Arc<ArcSwap<Option<ValidatorSigner>>>;
+
 /// Have that many iterations in the timer instead of `loop` to prevent potential bugs from blocking
 /// the node
 const MAX_TIMER_ITERS: usize = 20;
 
+/// Converts a wall-clock `Duration` into a whole number of `tick_duration`-sized ticks.
+fn duration_to_ticks(duration: Duration, tick_duration: Duration) -> Tick {
+    Tick((duration.as_nanos() / tick_duration.as_nanos()) as u64)
+}
+
 struct DoomslugTimer {
-    started: Instant,
-    last_endorsement_sent: Instant,
+    started: Tick,
+    last_endorsement_sent: Tick,
     height: BlockHeight,
-    endorsement_delay: Duration,
-    min_delay: Duration,
-    delay_step: Duration,
-    max_delay: Duration,
+    endorsement_delay: Tick,
+    min_delay: Tick,
+    delay_step: Tick,
+    max_delay: Tick,
 }
 
 impl DoomslugTimer {
@@ -26,8 +39,8 @@ impl DoomslugTimer {
     /// * `n` - number of heights since the last block with doomslug finality
     ///
     /// # Returns
-    /// Duration to sleep
-    pub fn get_delay(&self, n: BlockHeightDelta) -> Duration {
+    /// Number of ticks to sleep
+    pub fn get_delay(&self, n: BlockHeightDelta) -> Tick {
         let n32 = u32::try_from(n).unwrap_or(u32::MAX);
         std::cmp::min(
             self.max_delay,
@@ -43,6 +56,10 @@ struct DoomslugTip {
 
 struct Doomslug {
     clock: Clock,
+    /// The instant the `Doomslug` was constructed; ticks are counted relative to this.
+    genesis: Instant,
+    /// The size of a single tick, used to convert between `Instant`/`Duration` and `Tick`.
+    tick_duration: Duration,
     /// Largest target height for which we issued an approval
     largest_target_height: BlockHeight,
     /// Largest height for which we saw a block containing 1/2 endorsements in it
@@ -59,14 +76,18 @@ impl Doomslug {
     fn new(
         clock: Clock,
         largest_target_height: BlockHeight,
+        tick_duration: Duration,
         endorsement_delay: Duration,
         min_delay: Duration,
         delay_step: Duration,
         max_delay: Duration,
     ) -> Self {
-        let now = clock.now();
+        let genesis = clock.now();
+        let now_tick = clock.now_tick(genesis, tick_duration);
         Self {
             clock,
+            genesis,
+            tick_duration,
             largest_target_height,
             largest_final_height: 0,
             tip: DoomslugTip {
@@ -75,17 +96,34 @@ impl Doomslug {
             },
             endorsement_pending: false,
             timer: DoomslugTimer {
-                started: now,
-                last_endorsement_sent: now,
+                started: now_tick,
+                last_endorsement_sent: now_tick,
                 height: 0,
-                endorsement_delay,
-                min_delay,
-                delay_step,
-                max_delay,
+                endorsement_delay: duration_to_ticks(endorsement_delay, tick_duration),
+                min_delay: duration_to_ticks(min_delay, tick_duration),
+                delay_step: duration_to_ticks(delay_step, tick_duration),
+                max_delay: duration_to_ticks(max_delay, tick_duration),
             },
         }
     }
 
+    /// Returns the current tick, relative to this `Doomslug`'s genesis instant.
+    fn now_tick(&self) -> Tick {
+        self.clock.now_tick(self.genesis, self.tick_duration)
+    }
+
+    /// The largest height for which we've seen a block with Doomslug (>=1/2 stake) finality.
+    pub fn largest_final_height(&self) -> BlockHeight {
+        self.largest_final_height
+    }
+
+    /// Advances `largest_final_height` once a `DoomslugApprovalTracker` determines that a higher
+    /// height has crossed the finality threshold. The new value should be passed back in as
+    /// `last_final_height` on the next `set_tip`.
+    pub fn update_largest_final_height(&mut self, height: BlockHeight) {
+        self.largest_final_height = std::cmp::max(self.largest_final_height, height);
+    }
+
     /// Updates the current tip of the chain. Restarts the timer accordingly.
     ///
     /// # Arguments
@@ -102,30 +140,17 @@ impl Doomslug {
 
         self.largest_final_height = last_final_height;
         self.timer.height = height + 1;
-        self.timer.started = self.clock.now();
+        self.timer.started = self.now_tick();
 
         self.endorsement_pending = true;
     }
 
-    fn create_approval(
-        &self,
-        target_height: BlockHeight,
-        //signer: &Option<Arc<ValidatorSigner>>,
-    ) -> Option<Approval> {
-        //signer.as_ref().map(|signer| {
-        Some(Approval::new(
-            self.tip.block_hash,
-            self.tip.height,
-            target_height,
-        ))
-        //})
-    }
-
-    /// Returns a vector of approvals that need to be sent to other block producers as a result
-    /// of processing the timers.
-    fn process_timer(&mut self) -> Vec<Approval> {
-        let now = self.clock.now();
-        let mut approvals = vec![];
+    /// Runs the timer loop and returns the raw `(inner, target_height)` votes it produced,
+    /// without signing them. Shared by `process_timer` (one signature per vote) and
+    /// `process_timer_coalesced` (one signature for the whole batch).
+    fn collect_timer_votes(&mut self) -> Vec<(ApprovalInner, BlockHeight)> {
+        let now = self.now_tick();
+        let mut votes = vec![];
         for _ in 0..MAX_TIMER_ITERS {
             let skip_delay = self
                 .timer
@@ -134,7 +159,7 @@ impl Doomslug {
             // The `endorsement_delay` is time to send approval to the block producer at `timer.height`,
             // while the `skip_delay` is the time before sending the approval to BP of `timer_height + 1`,
             // so it makes sense for them to be at least 2x apart
-            debug_assert!(skip_delay >= 2 * self.timer.endorsement_delay);
+            debug_assert!(skip_delay >= self.timer.endorsement_delay * 2);
 
             let tip_height = self.tip.height;
             if self.endorsement_pending
@@ -143,9 +168,9 @@ impl Doomslug {
                 if tip_height >= self.largest_target_height {
                     self.largest_target_height = tip_height + 1;
 
-                    if let Some(approval) = self.create_approval(tip_height + 1) {
-                        approvals.push(approval);
-                    }
+                    let target_height = tip_height + 1;
+                    let inner = ApprovalInner::new(&self.tip.block_hash, tip_height, target_height);
+                    votes.push((inner, target_height));
                 }
 
                 self.timer.last_endorsement_sent = now;
@@ -158,9 +183,9 @@ impl Doomslug {
                 self.largest_target_height =
                     std::cmp::max(self.timer.height + 1, self.largest_target_height);
 
-                if let Some(approval) = self.create_approval(self.timer.height + 1) {
-                    approvals.push(approval);
-                }
+                let target_height = self.timer.height + 1;
+                let inner = ApprovalInner::new(&self.tip.block_hash, self.tip.height, target_height);
+                votes.push((inner, target_height));
 
                 // Restart the timer
                 self.timer.started += skip_delay;
@@ -169,23 +194,61 @@ impl Doomslug {
                 break;
             }
         }
-        approvals
+        votes
+    }
+
+    /// Returns a vector of approvals that need to be sent to other block producers as a result
+    /// of processing the timers. Each approval carries its own signature; this is the common
+    /// path when there is at most one vote to report.
+    fn process_timer(&mut self, signer: &ValidatorSignerHandle) -> Vec<Approval> {
+        let votes = self.collect_timer_votes();
+        let guard = signer.load();
+        let Some(signer) = guard.as_ref().as_ref() else {
+            return vec![];
+        };
+        votes
+            .into_iter()
+            .map(|(inner, target_height)| Approval::from_inner(inner, target_height, signer))
+            .collect()
+    }
+
+    /// Like `process_timer`, but coalesces all the votes produced in this pass into a single
+    /// `CoalescedApproval` covered by one signature, for when a node has fallen behind several
+    /// heights and would otherwise need a burst of individually-signed approvals.
+    fn process_timer_coalesced(
+        &mut self,
+        signer: &ValidatorSignerHandle,
+    ) -> Option<CoalescedApproval> {
+        let votes = self.collect_timer_votes();
+        if votes.is_empty() {
+            return None;
+        }
+        let guard = signer.load();
+        let signer = guard.as_ref().as_ref()?;
+        Some(CoalescedApproval::new(votes, signer))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chain_model::block::ApprovalInner;
     use chain_model::clock::Clock;
     use chain_model::crypto::hash;
+    use ed25519_dalek::SigningKey;
+
+    fn test_signer_handle() -> ValidatorSignerHandle {
+        let signer = ValidatorSigner::new("test.near".to_string(), SigningKey::from_bytes(&[0; 32]));
+        Arc::new(ArcSwap::from_pointee(Some(signer)))
+    }
 
     #[test]
     fn test_endorsements_and_skips_basic() {
         let mut clock = Clock::fake_new();
+        let signer = test_signer_handle();
         let mut ds = Doomslug::new(
             clock.clone(),
             0,
+            Duration::from_millis(1),
             Duration::from_millis(400),
             Duration::from_millis(1000),
             Duration::from_millis(100),
@@ -195,23 +258,23 @@ mod tests {
         // Set a new tip, must produce an endorsement
         ds.set_tip(hash(&[123]), 1, 1);
         clock.advance(Duration::from_millis(399));
-        assert_eq!(ds.process_timer().len(), 0);
+        assert_eq!(ds.process_timer(&signer).len(), 0);
         clock.advance(Duration::from_millis(1));
-        let approval = ds.process_timer().into_iter().nth(0).unwrap();
+        let approval = ds.process_timer(&signer).into_iter().nth(0).unwrap();
         assert_eq!(approval.inner, ApprovalInner::Endorsement(hash(&[123])));
         assert_eq!(approval.target_height, 2);
 
         // Same tip => no approval
-        assert_eq!(ds.process_timer(), vec![]);
+        assert_eq!(ds.process_timer(&signer), vec![]);
 
         // The block was `ds_final` and therefore started the timer.
         // Try checking before one second expires
         clock.advance(Duration::from_millis(599));
-        assert_eq!(ds.process_timer(), vec![]);
+        assert_eq!(ds.process_timer(&signer), vec![]);
 
         // But one second should trigger the skip
         clock.advance(Duration::from_millis(1));
-        match ds.process_timer() {
+        match ds.process_timer(&signer) {
             approvals if approvals.is_empty() => assert!(false),
             approvals => {
                 assert_eq!(approvals[0].inner, ApprovalInner::Skip(1));
@@ -222,7 +285,7 @@ mod tests {
         // Not processing a block at height 2 should not produce an approval
         ds.set_tip(hash(&[234]), 2, 0);
         clock.advance(Duration::from_millis(400));
-        assert_eq!(ds.process_timer(), vec![]);
+        assert_eq!(ds.process_timer(&signer), vec![]);
 
         // Go forward more so we have 1 second
         clock.advance(Duration::from_millis(600));
@@ -230,7 +293,7 @@ mod tests {
         // But at height 3 should (also neither block has finality set, keep last final at 0 for now)
         ds.set_tip(hash(&[31]), 3, 0);
         clock.advance(Duration::from_millis(400));
-        let approval = ds.process_timer().into_iter().nth(0).unwrap();
+        let approval = ds.process_timer(&signer).into_iter().nth(0).unwrap();
         assert_eq!(approval.inner, ApprovalInner::Endorsement(hash(&[31])));
         assert_eq!(approval.target_height, 4);
 
@@ -238,10 +301,10 @@ mod tests {
         clock.advance(Duration::from_millis(600));
 
         clock.advance(Duration::from_millis(199));
-        assert_eq!(ds.process_timer(), vec![]);
+        assert_eq!(ds.process_timer(&signer), vec![]);
 
         clock.advance(Duration::from_millis(1));
-        match ds.process_timer() {
+        match ds.process_timer(&signer) {
             approvals if approvals.is_empty() => assert!(false),
             approvals if approvals.len() == 1 => {
                 assert_eq!(approvals[0].inner, ApprovalInner::Skip(3));
@@ -255,10 +318,10 @@ mod tests {
 
         // Now skip 5 (the extra delay is 200+300 = 500)
         clock.advance(Duration::from_millis(499));
-        assert_eq!(ds.process_timer(), vec![]);
+        assert_eq!(ds.process_timer(&signer), vec![]);
 
         clock.advance(Duration::from_millis(1));
-        match ds.process_timer() {
+        match ds.process_timer(&signer) {
             approvals if approvals.is_empty() => assert!(false),
             approvals => {
                 assert_eq!(approvals[0].inner, ApprovalInner::Skip(3));
@@ -271,10 +334,10 @@ mod tests {
 
         // Skip 6 (the extra delay is 0+200+300+400 = 900)
         clock.advance(Duration::from_millis(899));
-        assert_eq!(ds.process_timer(), vec![]);
+        assert_eq!(ds.process_timer(&signer), vec![]);
 
         clock.advance(Duration::from_millis(1));
-        match ds.process_timer() {
+        match ds.process_timer(&signer) {
             approvals if approvals.is_empty() => assert!(false),
             approvals => {
                 assert_eq!(approvals[0].inner, ApprovalInner::Skip(3));
@@ -282,4 +345,70 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_no_signer_produces_no_approvals() {
+        let mut clock = Clock::fake_new();
+        let no_signer: ValidatorSignerHandle = Arc::new(ArcSwap::from_pointee(None));
+        let mut ds = Doomslug::new(
+            clock.clone(),
+            0,
+            Duration::from_millis(1),
+            Duration::from_millis(400),
+            Duration::from_millis(1000),
+            Duration::from_millis(100),
+            Duration::from_millis(3000),
+        );
+
+        ds.set_tip(hash(&[123]), 1, 1);
+        clock.advance(Duration::from_millis(400));
+        assert_eq!(ds.process_timer(&no_signer), vec![]);
+    }
+
+    #[test]
+    fn test_process_timer_coalesced_batches_accumulated_votes() {
+        let mut clock = Clock::fake_new();
+        let signer = test_signer_handle();
+        let mut ds = Doomslug::new(
+            clock.clone(),
+            0,
+            Duration::from_millis(1),
+            Duration::from_millis(400),
+            Duration::from_millis(1000),
+            Duration::from_millis(100),
+            Duration::from_millis(3000),
+        );
+
+        // No votes pending yet.
+        assert!(ds.process_timer_coalesced(&signer).is_none());
+
+        // The endorsement alone still produces a (single-vote) coalesced approval.
+        ds.set_tip(hash(&[123]), 1, 1);
+        clock.advance(Duration::from_millis(400));
+        let coalesced = ds.process_timer_coalesced(&signer).unwrap();
+        assert_eq!(
+            coalesced.inner_votes,
+            vec![(ApprovalInner::Endorsement(hash(&[123])), 2)]
+        );
+
+        let expanded = coalesced
+            .verify_and_expand(&signer.load().as_ref().as_ref().unwrap().public_key())
+            .unwrap();
+        assert_eq!(expanded.len(), 1);
+        assert_eq!(expanded[0].inner, ApprovalInner::Endorsement(hash(&[123])));
+        assert_eq!(expanded[0].target_height, 2);
+
+        // Falling behind: the skip deadlines at ticks 1000 and 2000 both pass before the next
+        // call, so both skips come back in one batch under a single signature.
+        clock.advance(Duration::from_millis(1600));
+        let coalesced = ds.process_timer_coalesced(&signer).unwrap();
+        assert_eq!(
+            coalesced.inner_votes,
+            vec![(ApprovalInner::Skip(1), 3), (ApprovalInner::Skip(1), 4)]
+        );
+        let expanded = coalesced
+            .verify_and_expand(&signer.load().as_ref().as_ref().unwrap().public_key())
+            .unwrap();
+        assert_eq!(expanded.len(), 2);
+    }
 }
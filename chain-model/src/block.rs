@@ -1,8 +1,31 @@
 use crate::crypto::CryptoHash;
-use crate::types::BlockHeight;
+use crate::types::{AccountId, BlockHeight};
+use crate::validator_signer::ValidatorSigner;
+use borsh::{BorshDeserialize, BorshSerialize};
+use ed25519_dalek::{Signature, SignatureError, Verifier, VerifyingKey};
+
+/// Computes the digest that's actually signed for an `(inner, target_height)` approval vote:
+/// Borsh-serializes the pair and hashes it. Shared by signing (`ValidatorSigner::sign_approval`)
+/// and verification (`DoomslugApprovalTracker::register_approval`,
+/// `AggregatedEndorsements::verify`) so both sides always agree on what bytes a signature covers.
+pub fn approval_message(inner: &ApprovalInner, target_height: BlockHeight) -> CryptoHash {
+    let payload = (inner, target_height)
+        .try_to_vec()
+        .expect("borsh serialization of an approval payload cannot fail");
+    CryptoHash::hash_bytes(&payload)
+}
+
+/// Computes the digest signed for a batch of `(inner, target_height)` votes, as used by
+/// `CoalescedApproval`.
+pub fn approval_batch_message(votes: &[(ApprovalInner, BlockHeight)]) -> CryptoHash {
+    let payload = votes
+        .try_to_vec()
+        .expect("borsh serialization of an approval batch cannot fail");
+    CryptoHash::hash_bytes(&payload)
+}
 
 /// The part of the block approval that is different for endorsements and skips
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, BorshSerialize, BorshDeserialize)]
 pub enum ApprovalInner {
     Endorsement(CryptoHash),
     Skip(BlockHeight),
@@ -22,9 +45,12 @@ impl ApprovalInner {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Approval {
     pub inner: ApprovalInner,
     pub target_height: BlockHeight,
+    pub signature: Signature,
+    pub account_id: AccountId,
 }
 
 impl Approval {
@@ -32,22 +58,122 @@ impl Approval {
         parent_hash: CryptoHash,
         parent_height: BlockHeight,
         target_height: BlockHeight,
-        //signer: &ValidatorSigner,
+        signer: &ValidatorSigner,
     ) -> Self {
         let inner = ApprovalInner::new(&parent_hash, parent_height, target_height);
-        //let signature = signer.sign_approval(&inner, target_height);
+        Approval::from_inner(inner, target_height, signer)
+    }
+
+    /// Builds a signed `Approval` from an already-computed `ApprovalInner`, e.g. when the vote
+    /// was derived elsewhere and only needs to be signed.
+    pub fn from_inner(
+        inner: ApprovalInner,
+        target_height: BlockHeight,
+        signer: &ValidatorSigner,
+    ) -> Self {
+        let signature = signer.sign_approval(&inner, target_height);
         Approval {
             inner,
             target_height,
-            //signature,
-            //account_id: signer.validator_id().clone(),
+            signature,
+            account_id: signer.validator_id().clone(),
+        }
+    }
+}
+
+/// Several endorsement/skip votes from the same validator, covered by a single signature over
+/// the Borsh-serialized vector of `(inner, target_height)` pairs, instead of one signature per
+/// vote. Used when a node that has fallen behind several heights needs to report them all at
+/// once without a burst of individually-signed approvals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoalescedApproval {
+    pub inner_votes: Vec<(ApprovalInner, BlockHeight)>,
+    pub account_id: AccountId,
+    pub signature: Signature,
+}
+
+/// One `(inner, target_height)` vote out of a `CoalescedApproval` whose batch signature has
+/// already been checked by `CoalescedApproval::verify_and_expand`.
+///
+/// Deliberately not an `Approval`: the batch signature covers the whole vote vector, not this
+/// vote alone, so it isn't a valid individual signature and must not be presented as one (e.g. to
+/// `DoomslugApprovalTracker::register_approval`, which expects `Approval::signature` to verify
+/// against just this vote). Feed these into
+/// `DoomslugApprovalTracker::register_verified_vote` instead, which trusts that the caller has
+/// already authenticated them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedVote {
+    pub inner: ApprovalInner,
+    pub target_height: BlockHeight,
+    pub account_id: AccountId,
+}
+
+impl CoalescedApproval {
+    pub fn new(inner_votes: Vec<(ApprovalInner, BlockHeight)>, signer: &ValidatorSigner) -> Self {
+        let signature = signer.sign_approval_batch(&inner_votes);
+        CoalescedApproval {
+            inner_votes,
+            account_id: signer.validator_id().clone(),
+            signature,
         }
     }
+
+    /// Verifies the single signature against the concatenated payload of all votes, then
+    /// re-expands the batch into individually-trusted `VerifiedVote`s for downstream processing.
+    pub fn verify_and_expand(
+        &self,
+        public_key: &VerifyingKey,
+    ) -> Result<Vec<VerifiedVote>, SignatureError> {
+        public_key.verify(approval_batch_message(&self.inner_votes).as_ref(), &self.signature)?;
+
+        Ok(self
+            .inner_votes
+            .iter()
+            .cloned()
+            .map(|(inner, target_height)| VerifiedVote {
+                inner,
+                target_height,
+                account_id: self.account_id.clone(),
+            })
+            .collect())
+    }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::hash;
+    use ed25519_dalek::SigningKey;
 
     #[test]
     fn it_works() {}
+
+    #[test]
+    fn coalesced_approval_round_trips_through_verify_and_expand() {
+        let signer = ValidatorSigner::new("alice.near".to_string(), SigningKey::from_bytes(&[1; 32]));
+        let votes = vec![
+            (ApprovalInner::Endorsement(hash(&[1])), 2),
+            (ApprovalInner::Skip(2), 4),
+        ];
+
+        let coalesced = CoalescedApproval::new(votes.clone(), &signer);
+        let expanded = coalesced.verify_and_expand(&signer.public_key()).unwrap();
+
+        assert_eq!(expanded.len(), votes.len());
+        for (vote, (inner, target_height)) in expanded.iter().zip(votes.iter()) {
+            assert_eq!(&vote.inner, inner);
+            assert_eq!(vote.target_height, *target_height);
+            assert_eq!(vote.account_id, "alice.near");
+        }
+    }
+
+    #[test]
+    fn coalesced_approval_rejects_wrong_key() {
+        let signer = ValidatorSigner::new("alice.near".to_string(), SigningKey::from_bytes(&[1; 32]));
+        let other = ValidatorSigner::new("bob.near".to_string(), SigningKey::from_bytes(&[2; 32]));
+        let votes = vec![(ApprovalInner::Endorsement(hash(&[1])), 2)];
+
+        let coalesced = CoalescedApproval::new(votes, &signer);
+        assert!(coalesced.verify_and_expand(&other.public_key()).is_err());
+    }
 }
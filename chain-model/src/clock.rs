@@ -2,6 +2,40 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
+/// A fixed-size time quantum counted from some genesis `Instant`.
+///
+/// Doomslug timing is expressed in ticks rather than raw `Instant`/`Duration` arithmetic so that
+/// delay comparisons are plain integer comparisons: fully deterministic and reproducible under
+/// the `Clock::Fake` test harness, with no dependency on floating-point or sub-tick precision.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Tick(pub u64);
+
+impl Tick {
+    pub fn saturating_sub(self, other: Tick) -> Tick {
+        Tick(self.0.saturating_sub(other.0))
+    }
+}
+
+impl std::ops::Add for Tick {
+    type Output = Tick;
+    fn add(self, rhs: Tick) -> Tick {
+        Tick(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::AddAssign for Tick {
+    fn add_assign(&mut self, rhs: Tick) {
+        self.0 += rhs.0;
+    }
+}
+
+impl std::ops::Mul<u32> for Tick {
+    type Output = Tick;
+    fn mul(self, rhs: u32) -> Tick {
+        Tick(self.0 * rhs as u64)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Clock {
     Real,
@@ -21,6 +55,12 @@ impl Clock {
         }
     }
 
+    /// Returns the number of whole `tick_duration`-sized ticks elapsed since `genesis`, floored.
+    pub fn now_tick(&self, genesis: Instant, tick_duration: Duration) -> Tick {
+        let elapsed = self.now().saturating_duration_since(genesis);
+        Tick((elapsed.as_nanos() / tick_duration.as_nanos()) as u64)
+    }
+
     /// Advances the time for the Fake clock; no-op for the Real clock
     pub fn advance(&mut self, duration: Duration) {
         if let Clock::Fake(ref current_time) = self {
@@ -55,4 +95,19 @@ mod tests {
             Duration::from_millis(400)
         );
     }
+
+    #[test]
+    fn test_now_tick_floors_elapsed_duration() {
+        let mut clock = Clock::fake_new();
+        let genesis = clock.now();
+        let tick_duration = Duration::from_millis(100);
+
+        assert_eq!(clock.now_tick(genesis, tick_duration), Tick(0));
+
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(clock.now_tick(genesis, tick_duration), Tick(2));
+
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(clock.now_tick(genesis, tick_duration), Tick(3));
+    }
 }
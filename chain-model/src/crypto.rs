@@ -1,13 +1,27 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sha2::Digest;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 /// Calculates a hash of a bytes slice.
 pub fn hash(data: &[u8]) -> CryptoHash {
     CryptoHash::hash_bytes(data)
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, derive_more::AsRef, derive_more::AsMut)]
+#[derive(
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    derive_more::AsRef,
+    derive_more::AsMut,
+    BorshSerialize,
+    BorshDeserialize,
+)]
 #[as_ref(forward)]
 #[as_mut(forward)]
 pub struct CryptoHash(pub [u8; 32]);
@@ -63,3 +77,103 @@ impl fmt::Display for CryptoHash {
         self.to_base58_impl(|encoded| fmtr.write_str(encoded))
     }
 }
+
+/// Error returned when parsing a `CryptoHash` from a base58 string, or building one from a byte
+/// slice, fails.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseCryptoHashError {
+    InvalidBase58,
+    InvalidLength(usize),
+}
+
+impl fmt::Display for ParseCryptoHashError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseCryptoHashError::InvalidBase58 => write!(fmtr, "invalid base58 string"),
+            ParseCryptoHashError::InvalidLength(actual) => write!(
+                fmtr,
+                "invalid length: expected {} bytes, got {actual}",
+                CryptoHash::LENGTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseCryptoHashError {}
+
+impl TryFrom<&[u8]> for CryptoHash {
+    type Error = ParseCryptoHashError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        <[u8; Self::LENGTH]>::try_from(bytes)
+            .map(CryptoHash)
+            .map_err(|_| ParseCryptoHashError::InvalidLength(bytes.len()))
+    }
+}
+
+impl FromStr for CryptoHash {
+    type Err = ParseCryptoHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| ParseCryptoHashError::InvalidBase58)?;
+        CryptoHash::try_from(bytes.as_slice())
+    }
+}
+
+impl Serialize for CryptoHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_base58_impl(|encoded| serializer.serialize_str(encoded))
+    }
+}
+
+impl<'de> Deserialize<'de> for CryptoHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        encoded.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_round_trips_through_display() {
+        let h = hash(&[1, 2, 3]);
+        assert_eq!(h.to_string().parse::<CryptoHash>().unwrap(), h);
+    }
+
+    #[test]
+    fn from_str_rejects_invalid_base58() {
+        assert_eq!(
+            "not-valid-base58!".parse::<CryptoHash>(),
+            Err(ParseCryptoHashError::InvalidBase58)
+        );
+    }
+
+    #[test]
+    fn try_from_rejects_wrong_length() {
+        assert_eq!(
+            CryptoHash::try_from(&[0u8; 10][..]),
+            Err(ParseCryptoHashError::InvalidLength(10))
+        );
+    }
+
+    #[test]
+    fn serde_round_trips_as_base58_string() {
+        let h = hash(&[4, 5, 6]);
+        let json = serde_json::to_string(&h).unwrap();
+        assert_eq!(json, format!("\"{h}\""));
+        assert_eq!(serde_json::from_str::<CryptoHash>(&json).unwrap(), h);
+    }
+
+    #[test]
+    fn borsh_round_trips_as_raw_bytes() {
+        let h = hash(&[7, 8, 9]);
+        let bytes = h.try_to_vec().unwrap();
+        assert_eq!(bytes.len(), CryptoHash::LENGTH);
+        assert_eq!(CryptoHash::try_from_slice(&bytes).unwrap(), h);
+    }
+}
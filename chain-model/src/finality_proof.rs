@@ -0,0 +1,227 @@
+use crate::block::{approval_message, Approval, ApprovalInner};
+use crate::crypto::CryptoHash;
+use crate::types::{Balance, BlockHeight};
+use crate::validator_set::ValidatorSet;
+use bitvec::prelude::*;
+use ed25519_dalek::{Signature, SignatureError, VerifyingKey};
+use std::fmt;
+
+/// Why an `AggregatedEndorsements` proof failed to verify.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The number of set bits in `participation` doesn't match the number of signatures, so the
+    /// proof is malformed and can't be checked.
+    MismatchedParticipantCount,
+    /// One of the individual signatures didn't check out.
+    Signature(SignatureError),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::MismatchedParticipantCount => {
+                write!(f, "participation bitfield doesn't match the number of signatures")
+            }
+            VerifyError::Signature(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<SignatureError> for VerifyError {
+    fn from(err: SignatureError) -> Self {
+        VerifyError::Signature(err)
+    }
+}
+
+/// A compact, verifiable record of which validators endorsed `parent_hash` at `target_height`.
+///
+/// Ed25519 (unlike BLS) has no algebraic signature aggregation, so `signatures` doesn't collapse
+/// into a single scalar value -- it keeps one signature per participating validator, in
+/// validator-set order, alongside a bitfield recording who participated. `verify` checks all of
+/// them in one `ed25519_dalek::verify_batch` call against the shared `(inner, target_height)`
+/// message, rather than looping `public_key.verify` per signer.
+pub struct AggregatedEndorsements {
+    pub parent_hash: CryptoHash,
+    pub target_height: BlockHeight,
+    pub participation: BitVec,
+    signatures: Vec<Signature>,
+}
+
+impl AggregatedEndorsements {
+    /// Builds an aggregated endorsement proof out of individually-signed `Approval`s, keying the
+    /// proof on the parent/height of the first `Endorsement` vote found. Only `Endorsement`
+    /// votes matching that `(parent_hash, target_height)` are counted; anything else (a `Skip`,
+    /// or an endorsement of a different parent) is ignored, as is a second vote from a validator
+    /// already counted. Returns `None` if `approvals` contains no endorsement at all.
+    pub fn aggregate(approvals: &[Approval], validators: &ValidatorSet) -> Option<Self> {
+        let (parent_hash, target_height) = approvals.iter().find_map(|approval| match approval.inner {
+            ApprovalInner::Endorsement(parent_hash) => Some((parent_hash, approval.target_height)),
+            ApprovalInner::Skip(_) => None,
+        })?;
+
+        let mut participation = bitvec![0; validators.validators().len()];
+        let mut signatures: Vec<Option<Signature>> = vec![None; validators.validators().len()];
+
+        for approval in approvals {
+            if approval.inner != ApprovalInner::Endorsement(parent_hash)
+                || approval.target_height != target_height
+            {
+                continue;
+            }
+            let Some(index) = validators
+                .validators()
+                .iter()
+                .position(|v| v.account_id == approval.account_id)
+            else {
+                continue;
+            };
+            if participation[index] {
+                continue;
+            }
+            participation.set(index, true);
+            signatures[index] = Some(approval.signature);
+        }
+
+        // Stored in validator-set (i.e. bit) order, regardless of the order `approvals` arrived
+        // in, so `verify` can zip the bitfield's set bits straight against `signatures`.
+        let signatures = signatures.into_iter().flatten().collect();
+
+        Some(Self {
+            parent_hash,
+            target_height,
+            participation,
+            signatures,
+        })
+    }
+
+    /// Reconstructs the public-key set of participating validators from the bitfield and
+    /// batch-verifies their signatures in one `ed25519_dalek::verify_batch` call against the
+    /// shared `(inner, target_height)` message.
+    pub fn verify(&self, validators: &ValidatorSet) -> Result<(), VerifyError> {
+        if self.participation.count_ones() != self.signatures.len() {
+            return Err(VerifyError::MismatchedParticipantCount);
+        }
+        if self.signatures.is_empty() {
+            return Ok(());
+        }
+
+        let message = approval_message(&ApprovalInner::Endorsement(self.parent_hash), self.target_height);
+        let messages = vec![message.as_ref(); self.signatures.len()];
+        let public_keys: Vec<VerifyingKey> = self
+            .participation
+            .iter()
+            .by_vals()
+            .zip(validators.validators())
+            .filter(|(bit, _)| *bit)
+            .map(|(_, v)| v.public_key)
+            .collect();
+
+        ed25519_dalek::verify_batch(&messages, &self.signatures, &public_keys)?;
+        Ok(())
+    }
+
+    /// Total stake represented by validators whose bit is set, so the finality gadget can check
+    /// thresholds directly against the proof without re-deriving `(account_id, stake)` pairs.
+    pub fn total_stake(&self, validators: &ValidatorSet) -> Balance {
+        self.participation
+            .iter()
+            .by_vals()
+            .zip(validators.validators())
+            .filter(|(bit, _)| *bit)
+            .map(|(_, v)| v.stake)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash;
+    use crate::validator_set::ValidatorStake;
+    use crate::validator_signer::ValidatorSigner;
+    use ed25519_dalek::SigningKey;
+
+    fn validator_set() -> (ValidatorSet, Vec<ValidatorSigner>) {
+        let signers: Vec<ValidatorSigner> = vec![
+            ValidatorSigner::new("alice.near".to_string(), SigningKey::from_bytes(&[1; 32])),
+            ValidatorSigner::new("bob.near".to_string(), SigningKey::from_bytes(&[2; 32])),
+            ValidatorSigner::new("carol.near".to_string(), SigningKey::from_bytes(&[3; 32])),
+        ];
+        let validators = ValidatorSet::new(
+            signers
+                .iter()
+                .map(|s| ValidatorStake {
+                    account_id: s.validator_id().clone(),
+                    stake: 10,
+                    public_key: s.public_key(),
+                })
+                .collect(),
+        );
+        (validators, signers)
+    }
+
+    #[test]
+    fn aggregates_and_verifies_endorsements() {
+        let (validators, signers) = validator_set();
+        let parent = hash(&[7]);
+
+        let approvals: Vec<Approval> = signers
+            .iter()
+            .map(|s| Approval::new(parent, 10, 11, s))
+            .collect();
+
+        let proof = AggregatedEndorsements::aggregate(&approvals, &validators).unwrap();
+        assert_eq!(proof.participation.count_ones(), 3);
+        assert_eq!(proof.total_stake(&validators), 30);
+        assert!(proof.verify(&validators).is_ok());
+    }
+
+    #[test]
+    fn aggregate_returns_none_without_any_endorsement() {
+        let (validators, signers) = validator_set();
+
+        // Every vote is a skip, so there's no (parent_hash, target_height) to key the proof on.
+        let approvals: Vec<Approval> = signers
+            .iter()
+            .map(|s| Approval::from_inner(ApprovalInner::Skip(10), 12, s))
+            .collect();
+
+        assert!(AggregatedEndorsements::aggregate(&approvals, &validators).is_none());
+    }
+
+    #[test]
+    fn ignores_skips_and_unrelated_parents() {
+        let (validators, signers) = validator_set();
+        let parent = hash(&[7]);
+
+        let mut approvals: Vec<Approval> =
+            signers[..2].iter().map(|s| Approval::new(parent, 10, 11, s)).collect();
+        // Carol skips instead of endorsing, and also endorses an unrelated parent at the same
+        // target height; neither vote should be counted.
+        approvals.push(Approval::new(hash(&[8]), 10, 12, &signers[2]));
+        approvals.push(Approval::new(hash(&[8]), 10, 11, &signers[2]));
+
+        let proof = AggregatedEndorsements::aggregate(&approvals, &validators).unwrap();
+        assert_eq!(proof.participation.count_ones(), 2);
+        assert_eq!(proof.total_stake(&validators), 20);
+        assert!(proof.verify(&validators).is_ok());
+    }
+
+    #[test]
+    fn detects_tampered_participation_bitfield() {
+        let (validators, signers) = validator_set();
+        let parent = hash(&[7]);
+        let approvals: Vec<Approval> = signers
+            .iter()
+            .map(|s| Approval::new(parent, 10, 11, s))
+            .collect();
+
+        let mut proof = AggregatedEndorsements::aggregate(&approvals, &validators).unwrap();
+        proof.participation.set(0, false);
+
+        assert!(matches!(
+            proof.verify(&validators),
+            Err(VerifyError::MismatchedParticipantCount)
+        ));
+    }
+}
@@ -2,3 +2,7 @@
 pub type BlockHeight = u64;
 /// Block height delta that measures the difference between `BlockHeight`s.
 pub type BlockHeightDelta = u64;
+/// Account identifier of a validator.
+pub type AccountId = String;
+/// Amount of stake (or token balance) held by an account.
+pub type Balance = u128;
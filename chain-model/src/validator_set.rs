@@ -0,0 +1,41 @@
+use crate::types::{AccountId, Balance};
+use ed25519_dalek::VerifyingKey;
+
+/// A single validator's weight in the validator set, used to turn stake-weighted votes (like
+/// block approvals) into finality decisions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorStake {
+    pub account_id: AccountId,
+    pub stake: Balance,
+    /// Public counterpart of the validator's signing key, used to verify its approvals.
+    pub public_key: VerifyingKey,
+}
+
+/// The set of validators (and their stakes) for an epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ValidatorSet {
+    validators: Vec<ValidatorStake>,
+}
+
+impl ValidatorSet {
+    pub fn new(validators: Vec<ValidatorStake>) -> Self {
+        Self { validators }
+    }
+
+    pub fn validators(&self) -> &[ValidatorStake] {
+        &self.validators
+    }
+
+    pub fn total_stake(&self) -> Balance {
+        self.validators.iter().map(|v| v.stake).sum()
+    }
+
+    pub fn stake_of(&self, account_id: &AccountId) -> Option<Balance> {
+        self.get(account_id).map(|v| v.stake)
+    }
+
+    /// Looks up a validator's full stake entry (including its public key) by account id.
+    pub fn get(&self, account_id: &AccountId) -> Option<&ValidatorStake> {
+        self.validators.iter().find(|v| &v.account_id == account_id)
+    }
+}
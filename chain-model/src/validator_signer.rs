@@ -0,0 +1,45 @@
+use crate::block::{approval_batch_message, approval_message, ApprovalInner};
+use crate::types::{AccountId, BlockHeight};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+/// Signs messages (currently just block approvals) on behalf of a single validator account.
+///
+/// Deliberately holds nothing but the key material, so a node can swap in a new signer (e.g.
+/// behind an `ArcSwap`) when its validator key is rotated, without touching the rest of its
+/// state.
+pub struct ValidatorSigner {
+    account_id: AccountId,
+    signing_key: SigningKey,
+}
+
+impl ValidatorSigner {
+    pub fn new(account_id: AccountId, signing_key: SigningKey) -> Self {
+        Self {
+            account_id,
+            signing_key,
+        }
+    }
+
+    pub fn validator_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    /// The public counterpart of this signer's key, as published in the validator set so that
+    /// other nodes can verify approvals signed by it.
+    pub fn public_key(&self) -> VerifyingKey {
+        self.signing_key.verifying_key()
+    }
+
+    /// Signs the `(inner, target_height)` payload of an approval: Borsh-serializes the tuple,
+    /// hashes it, and signs the digest.
+    pub fn sign_approval(&self, inner: &ApprovalInner, target_height: BlockHeight) -> Signature {
+        self.signing_key.sign(approval_message(inner, target_height).as_ref())
+    }
+
+    /// Signs a batch of `(inner, target_height)` votes with a single signature over the
+    /// Borsh-serialized vector, so a node with several consecutive skips to report doesn't need
+    /// one signature per vote.
+    pub fn sign_approval_batch(&self, votes: &[(ApprovalInner, BlockHeight)]) -> Signature {
+        self.signing_key.sign(approval_batch_message(votes).as_ref())
+    }
+}